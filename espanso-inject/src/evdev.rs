@@ -0,0 +1,374 @@
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::fs;
+use std::os::raw::{c_char, c_int, c_void};
+use std::path::Path;
+
+use super::keys::Key;
+use super::{InjectionOptions, Injector, InjectorCreationOptions, KeyboardConfig};
+
+// Default xkb modifier bit indices scanned when building the lookup maps
+// (Shift, Mod1/Alt, Mod5/AltGr in the default xkbcommon modifier list),
+// and the default maximum number of modifiers combined in a single entry.
+const DEFAULT_MODIFIERS: &[u32] = &[0, 3, 7]; // Shift, Mod1 (Alt), Mod5 (AltGr)
+const DEFAULT_MAX_MODIFIER_COMBINATION_LEN: i32 = 2;
+
+// Maps each xkb modifier bit index above to the real evdev keycode that
+// must be held down to assert it (KEY_LEFTSHIFT, KEY_LEFTALT, KEY_RIGHTALT).
+// `xkb_state_update_mask` only ever sees the bit index, but the injector
+// has to press an actual key, so the two must be kept as separate tables.
+const MODIFIER_BIT_TO_EVDEV_KEYCODE: &[(u32, u32)] = &[(0, 42), (3, 56), (7, 100)];
+
+fn modifier_bit_to_evdev_keycode(bit: u32) -> Option<u32> {
+  MODIFIER_BIT_TO_EVDEV_KEYCODE
+    .iter()
+    .find(|(b, _)| *b == bit)
+    .map(|(_, keycode)| *keycode)
+}
+
+// evdev keycodes are offset by 8 with respect to the xkb keycodes used by
+// libxkbcommon (the first 8 xkb keycodes are reserved).
+const EVDEV_XKB_KEYCODE_OFFSET: u32 = 8;
+const MIN_EVDEV_KEYCODE: u32 = 1;
+const MAX_EVDEV_KEYCODE: u32 = 248;
+
+pub struct EVDEVInjector {
+  lookup_map: HashMap<char, (u32, Vec<u32>)>,
+}
+
+// The serialized form of the lookup tables, together with enough
+// information to tell whether they're still valid for the current
+// keyboard configuration.
+#[derive(Serialize, Deserialize)]
+struct KeymapCache {
+  rmlvo: CachedKeyboardConfig,
+  keymap_hash: u64,
+  lookup_map: HashMap<char, (u32, Vec<u32>)>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq)]
+struct CachedKeyboardConfig {
+  rules: Option<String>,
+  model: Option<String>,
+  layout: Option<String>,
+  variant: Option<String>,
+  options: Option<String>,
+}
+
+impl From<&Option<KeyboardConfig>> for CachedKeyboardConfig {
+  fn from(config: &Option<KeyboardConfig>) -> Self {
+    match config {
+      Some(config) => CachedKeyboardConfig {
+        rules: config.rules.clone(),
+        model: config.model.clone(),
+        layout: config.layout.clone(),
+        variant: config.variant.clone(),
+        options: config.options.clone(),
+      },
+      None => CachedKeyboardConfig {
+        rules: None,
+        model: None,
+        layout: None,
+        variant: None,
+        options: None,
+      },
+    }
+  }
+}
+
+impl EVDEVInjector {
+  pub fn new(options: InjectorCreationOptions) -> Result<EVDEVInjector> {
+    let modifiers = options
+      .evdev_modifiers
+      .clone()
+      .unwrap_or_else(|| DEFAULT_MODIFIERS.to_vec());
+    let max_modifier_combination_len = options
+      .evdev_max_modifier_combination_len
+      .unwrap_or(DEFAULT_MAX_MODIFIER_COMBINATION_LEN);
+
+    let rmlvo: CachedKeyboardConfig = (&options.evdev_keyboard_rmlvo).into();
+    let keymap = XkbKeymap::resolve(&options.evdev_keyboard_rmlvo)?;
+    let keymap_hash = keymap.hash();
+
+    if let Some(cache_path) = &options.evdev_keymap_cache_path {
+      if let Some(cache) = try_load_cache(cache_path, &rmlvo, keymap_hash) {
+        info!("loaded evdev lookup tables from cache: {:?}", cache_path);
+        return Ok(EVDEVInjector {
+          lookup_map: cache.lookup_map,
+        });
+      }
+    }
+
+    let lookup_map = build_lookup_map(&keymap, &modifiers, max_modifier_combination_len);
+
+    if let Some(cache_path) = &options.evdev_keymap_cache_path {
+      let cache = KeymapCache {
+        rmlvo,
+        keymap_hash,
+        lookup_map: lookup_map.clone(),
+      };
+      if let Err(error) = save_cache(cache_path, &cache) {
+        warn!("unable to write evdev keymap cache: {}", error);
+      }
+    }
+
+    Ok(EVDEVInjector { lookup_map })
+  }
+
+  fn keycode_and_modifiers_for_char(&self, c: char) -> Option<&(u32, Vec<u32>)> {
+    self.lookup_map.get(&c)
+  }
+}
+
+impl Injector for EVDEVInjector {
+  fn send_string(&self, string: &str, options: InjectionOptions) -> Result<()> {
+    let keys: Vec<char> = string.chars().collect();
+    self.send_chars(&keys, options)
+  }
+
+  fn send_keys(&self, keys: &[Key], options: InjectionOptions) -> Result<()> {
+    let chars: Vec<char> = keys
+      .iter()
+      .filter_map(|key| match key {
+        Key::RAW(c) => Some(*c),
+        _ => None,
+      })
+      .collect();
+    self.send_chars(&chars, options)
+  }
+
+  fn send_key_combination(&self, keys: &[Key], options: InjectionOptions) -> Result<()> {
+    self.send_keys(keys, options)
+  }
+}
+
+impl EVDEVInjector {
+  fn send_chars(&self, chars: &[char], _options: InjectionOptions) -> Result<()> {
+    for c in chars {
+      let (keycode, modifiers) = self
+        .keycode_and_modifiers_for_char(*c)
+        .ok_or_else(|| anyhow!("no evdev keycode mapping found for char '{}'", c))?;
+
+      send_key_event(*keycode, modifiers, true);
+      send_key_event(*keycode, modifiers, false);
+    }
+    Ok(())
+  }
+}
+
+// Posts a synthetic evdev key event through the uinput device created by
+// the native bridge, mirroring the other platform-specific bridge calls.
+extern "C" {
+  fn send_evdev_key_event(keycode: u32, modifiers: *const u32, modifiers_len: i32, key_down: bool);
+}
+
+fn send_key_event(keycode: u32, modifiers: &[u32], key_down: bool) {
+  unsafe {
+    send_evdev_key_event(keycode, modifiers.as_ptr(), modifiers.len() as i32, key_down);
+  }
+}
+
+// Minimal libxkbcommon bindings, used both to resolve the char -> keycode
+// lookup table and to obtain a stable representation of the current
+// keymap to hash for cache invalidation.
+#[repr(C)]
+struct XkbRuleNames {
+  rules: *const c_char,
+  model: *const c_char,
+  layout: *const c_char,
+  variant: *const c_char,
+  options: *const c_char,
+}
+
+const XKB_KEYMAP_FORMAT_TEXT_V1: c_int = 1;
+const XKB_KEYMAP_COMPILE_NO_FLAGS: c_int = 0;
+const XKB_CONTEXT_NO_FLAGS: c_int = 0;
+
+extern "C" {
+  fn xkb_context_new(flags: c_int) -> *mut c_void;
+  fn xkb_context_unref(context: *mut c_void);
+  fn xkb_keymap_new_from_names(context: *mut c_void, names: *const XkbRuleNames, flags: c_int) -> *mut c_void;
+  fn xkb_keymap_unref(keymap: *mut c_void);
+  fn xkb_keymap_get_as_string(keymap: *mut c_void, format: c_int) -> *mut c_char;
+  fn xkb_state_new(keymap: *mut c_void) -> *mut c_void;
+  fn xkb_state_unref(state: *mut c_void);
+  fn xkb_state_update_mask(
+    state: *mut c_void,
+    depressed_mods: u32,
+    latched_mods: u32,
+    locked_mods: u32,
+    depressed_layout: u32,
+    latched_layout: u32,
+    locked_layout: u32,
+  ) -> c_int;
+  fn xkb_state_key_get_utf8(state: *mut c_void, keycode: u32, buffer: *mut c_char, size: usize) -> c_int;
+  fn free(ptr: *mut c_void);
+}
+
+// Owns the xkbcommon context/keymap used to resolve the lookup table and
+// the keymap hash, so both are derived from the exact same compiled
+// keymap rather than from the raw RMLVO request.
+struct XkbKeymap {
+  context: *mut c_void,
+  keymap: *mut c_void,
+  as_string: String,
+}
+
+impl XkbKeymap {
+  fn resolve(rmlvo: &Option<KeyboardConfig>) -> Result<XkbKeymap> {
+    // Keep the CStrings alive for the duration of the FFI call below.
+    let rules = CString::new(rmlvo.as_ref().and_then(|c| c.rules.clone()).unwrap_or_default())?;
+    let model = CString::new(rmlvo.as_ref().and_then(|c| c.model.clone()).unwrap_or_default())?;
+    let layout = CString::new(rmlvo.as_ref().and_then(|c| c.layout.clone()).unwrap_or_default())?;
+    let variant = CString::new(rmlvo.as_ref().and_then(|c| c.variant.clone()).unwrap_or_default())?;
+    let options = CString::new(rmlvo.as_ref().and_then(|c| c.options.clone()).unwrap_or_default())?;
+
+    unsafe {
+      let context = xkb_context_new(XKB_CONTEXT_NO_FLAGS);
+      if context.is_null() {
+        return Err(anyhow!("unable to create xkb context"));
+      }
+
+      let names = XkbRuleNames {
+        rules: rules.as_ptr(),
+        model: model.as_ptr(),
+        layout: layout.as_ptr(),
+        variant: variant.as_ptr(),
+        options: options.as_ptr(),
+      };
+
+      let keymap = xkb_keymap_new_from_names(context, &names, XKB_KEYMAP_COMPILE_NO_FLAGS);
+      if keymap.is_null() {
+        xkb_context_unref(context);
+        return Err(anyhow!("unable to resolve xkb keymap from the given RMLVO config"));
+      }
+
+      let raw_string = xkb_keymap_get_as_string(keymap, XKB_KEYMAP_FORMAT_TEXT_V1);
+      let as_string = if raw_string.is_null() {
+        String::new()
+      } else {
+        let owned = CStr::from_ptr(raw_string).to_string_lossy().into_owned();
+        free(raw_string as *mut c_void);
+        owned
+      };
+
+      Ok(XkbKeymap {
+        context,
+        keymap,
+        as_string,
+      })
+    }
+  }
+
+  // Hashes the fully resolved xkb keymap (not just the RMLVO request that
+  // produced it), so a change in what the same RMLVO request compiles to
+  // (e.g. updated xkb data files or defaults) still invalidates the cache.
+  // The keymap is compiled by xkbcommon from the RMLVO names, not read from
+  // the X server, so changes applied only to the running server (such as
+  // `setxkbmap`/`xmodmap`) are not reflected in the hash.
+  fn hash(&self) -> u64 {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = DefaultHasher::new();
+    self.as_string.hash(&mut hasher);
+    hasher.finish()
+  }
+}
+
+impl Drop for XkbKeymap {
+  fn drop(&mut self) {
+    unsafe {
+      xkb_keymap_unref(self.keymap);
+      xkb_context_unref(self.context);
+    }
+  }
+}
+
+// Builds the char -> (keycode, modifiers) lookup map by enumerating every
+// combination of the given modifiers (up to the given length) against
+// every key in the resolved keymap, recording the first (simplest)
+// combination found to produce each character. This is combinatorial in
+// the number of modifiers, which is why the result is worth caching to
+// disk.
+fn build_lookup_map(keymap: &XkbKeymap, modifiers: &[u32], max_modifier_combination_len: i32) -> HashMap<char, (u32, Vec<u32>)> {
+  let mut lookup_map = HashMap::new();
+
+  let state = unsafe { xkb_state_new(keymap.keymap) };
+  if state.is_null() {
+    return lookup_map;
+  }
+
+  for combination_len in 0..=max_modifier_combination_len {
+    for combination in combinations(modifiers, combination_len as usize) {
+      let mods_mask = combination.iter().fold(0u32, |acc, &bit| acc | (1 << bit));
+
+      unsafe {
+        xkb_state_update_mask(state, mods_mask, 0, 0, 0, 0, 0);
+      }
+
+      for evdev_keycode in MIN_EVDEV_KEYCODE..MAX_EVDEV_KEYCODE {
+        let xkb_keycode = evdev_keycode + EVDEV_XKB_KEYCODE_OFFSET;
+
+        let mut buffer = [0 as c_char; 8];
+        let len = unsafe { xkb_state_key_get_utf8(state, xkb_keycode, buffer.as_mut_ptr(), buffer.len()) };
+        if len <= 0 {
+          continue;
+        }
+
+        let bytes: Vec<u8> = buffer[..len as usize].iter().map(|&b| b as u8).collect();
+        if let Some(c) = String::from_utf8_lossy(&bytes).chars().next() {
+          let modifier_keycodes: Vec<u32> = combination
+            .iter()
+            .filter_map(|&bit| modifier_bit_to_evdev_keycode(bit))
+            .collect();
+          lookup_map.entry(c).or_insert((evdev_keycode, modifier_keycodes));
+        }
+      }
+    }
+  }
+
+  unsafe {
+    xkb_state_unref(state);
+  }
+
+  lookup_map
+}
+
+fn combinations(items: &[u32], len: usize) -> Vec<Vec<u32>> {
+  if len == 0 {
+    return vec![vec![]];
+  }
+  if items.is_empty() {
+    return vec![];
+  }
+
+  let mut result = Vec::new();
+  for (i, &item) in items.iter().enumerate() {
+    for mut rest in combinations(&items[i + 1..], len - 1) {
+      rest.insert(0, item);
+      result.push(rest);
+    }
+  }
+  result
+}
+
+fn try_load_cache(path: &Path, rmlvo: &CachedKeyboardConfig, keymap_hash: u64) -> Option<KeymapCache> {
+  let content = fs::read(path).ok()?;
+  let cache: KeymapCache = bincode::deserialize(&content).ok()?;
+
+  if cache.rmlvo == *rmlvo && cache.keymap_hash == keymap_hash {
+    Some(cache)
+  } else {
+    None
+  }
+}
+
+fn save_cache(path: &Path, cache: &KeymapCache) -> Result<()> {
+  let serialized = bincode::serialize(cache)?;
+  fs::write(path, serialized)?;
+  Ok(())
+}
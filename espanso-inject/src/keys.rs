@@ -0,0 +1,31 @@
+/// Logical keys that can be injected, either standalone (`send_keys`) or
+/// as part of a combination (`send_key_combination`).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Key {
+  // Modifiers
+  ALT,
+  CTRL,
+  SHIFT,
+  META, // Command on macOS, Super/Windows key elsewhere
+
+  // Editing / navigation
+  BACKSPACE,
+  DELETE,
+  RETURN,
+  TAB,
+  ESCAPE,
+  SPACE,
+
+  ARROW_UP,
+  ARROW_DOWN,
+  ARROW_LEFT,
+  ARROW_RIGHT,
+  HOME,
+  END,
+  PAGE_UP,
+  PAGE_DOWN,
+
+  // A raw unicode character, used to fill in the non-modifier part of a
+  // combination (e.g. CTRL+C).
+  RAW(char),
+}
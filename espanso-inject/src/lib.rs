@@ -93,6 +93,12 @@ pub struct InjectorCreationOptions {
   // Can be used to overwrite the keymap configuration
   // used by espanso to inject key presses.
   evdev_keyboard_rmlvo: Option<KeyboardConfig>,
+
+  // Path to a file where the computed EVDEV lookup tables are cached,
+  // together with the RMLVO config and a hash of the resolved xkb keymap
+  // they were derived from. When present and the hash still matches the
+  // current keymap, the cache is loaded instead of recomputing the tables.
+  evdev_keymap_cache_path: Option<std::path::PathBuf>,
 }
 
 // This struct identifies the keyboard layout that
@@ -113,6 +119,7 @@ impl Default for InjectorCreationOptions {
       evdev_modifiers: None,
       evdev_max_modifier_combination_len: None,
       evdev_keyboard_rmlvo: None,
+      evdev_keymap_cache_path: None,
     }
   }
 }
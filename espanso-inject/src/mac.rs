@@ -0,0 +1,244 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::os::raw::c_void;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use super::keys::Key;
+use super::{InjectionOptions, Injector};
+
+type CGEventRef = *mut c_void;
+type CGEventSourceRef = *mut c_void;
+type CGEventFlags = u64;
+type CGKeyCode = u16;
+
+const K_CG_EVENT_FLAG_MASK_COMMAND: CGEventFlags = 1 << 20;
+const K_CG_EVENT_FLAG_MASK_ALTERNATE: CGEventFlags = 1 << 19; // Option
+const K_CG_EVENT_FLAG_MASK_CONTROL: CGEventFlags = 1 << 18;
+const K_CG_EVENT_FLAG_MASK_SHIFT: CGEventFlags = 1 << 17;
+
+const K_CG_HID_EVENT_TAP: u32 = 0;
+
+extern "C" {
+  fn CGEventCreateKeyboardEvent(source: CGEventSourceRef, virtual_key: CGKeyCode, key_down: bool) -> CGEventRef;
+  fn CGEventSetFlags(event: CGEventRef, flags: CGEventFlags);
+  fn CGEventPost(tap: u32, event: CGEventRef);
+  fn CFRelease(cf: *mut c_void);
+}
+
+// Text Input Source queries (TISCopyCurrentKeyboardInputSource /
+// TISGetInputSourceProperty) must be dispatched on the main thread: calling
+// them off the main queue triggers a `dispatch_assert_queue` crash. These
+// bindings let us hop onto the main run loop to resolve the full
+// char -> keycode table for the current layout, then cache the result so
+// lookups of chars already in the cache avoid the hop. A char that is not
+// in the cache makes the table be resolved again, through the same hop.
+extern "C" {
+  fn dispatch_get_main_queue() -> *mut c_void;
+  fn dispatch_sync_f(queue: *mut c_void, context: *mut c_void, work: extern "C" fn(*mut c_void));
+  fn pthread_main_np() -> i32;
+
+  fn TISCopyCurrentKeyboardInputSource() -> *mut c_void;
+  fn TISGetInputSourceProperty(input_source: *mut c_void, property_key: *const c_void) -> *const c_void;
+  fn CFDataGetBytePtr(data: *const c_void) -> *const u8;
+  fn LMGetKbdType() -> u8;
+  fn UCKeyTranslate(
+    key_layout_ptr: *const c_void,
+    virtual_key_code: u16,
+    key_action: u16,
+    modifier_key_state: u32,
+    keyboard_type: u32,
+    key_translate_options: u32,
+    dead_key_state: *mut u32,
+    max_string_length: usize,
+    actual_string_length: *mut usize,
+    unicode_string: *mut u16,
+  ) -> i32;
+
+  static kTISPropertyUnicodeKeyLayoutData: *const c_void;
+}
+
+const K_UC_KEY_ACTION_DOWN: u16 = 0;
+const K_UC_KEY_TRANSLATE_NO_DEAD_KEYS_MASK: u32 = 1;
+
+lazy_static! {
+  static ref KEYCODE_CACHE: Mutex<HashMap<char, CGKeyCode>> = Mutex::new(HashMap::new());
+}
+
+extern "C" fn build_keycode_table_on_main_thread(_context: *mut c_void) {
+  let table = build_keycode_table();
+  KEYCODE_CACHE.lock().unwrap().extend(table);
+}
+
+// Resolves the chars the current keyboard layout produces with no
+// modifiers held, by brute forcing UCKeyTranslate over the full
+// virtual-keycode range, using the layout data exposed by the current TIS
+// input source. Chars that need Shift or Option (e.g. uppercase letters)
+// are not in the table. Must only be called while already on the main
+// thread.
+fn build_keycode_table() -> HashMap<char, CGKeyCode> {
+  let mut table = HashMap::new();
+
+  unsafe {
+    let input_source = TISCopyCurrentKeyboardInputSource();
+    if input_source.is_null() {
+      return table;
+    }
+
+    let layout_data = TISGetInputSourceProperty(input_source, kTISPropertyUnicodeKeyLayoutData);
+    if layout_data.is_null() {
+      return table;
+    }
+
+    let layout_ptr = CFDataGetBytePtr(layout_data) as *const c_void;
+    let keyboard_type = LMGetKbdType() as u32;
+
+    for virtual_key_code in 0u16..128 {
+      let mut dead_key_state: u32 = 0;
+      let mut unicode_string = [0u16; 4];
+      let mut actual_length: usize = 0;
+
+      let status = UCKeyTranslate(
+        layout_ptr,
+        virtual_key_code,
+        K_UC_KEY_ACTION_DOWN,
+        0,
+        keyboard_type,
+        K_UC_KEY_TRANSLATE_NO_DEAD_KEYS_MASK,
+        &mut dead_key_state,
+        unicode_string.len(),
+        &mut actual_length,
+        unicode_string.as_mut_ptr(),
+      );
+
+      if status != 0 || actual_length == 0 {
+        continue;
+      }
+
+      if let Some(c) = String::from_utf16_lossy(&unicode_string[..actual_length]).chars().next() {
+        table.entry(c).or_insert(virtual_key_code);
+      }
+    }
+  }
+
+  table
+}
+
+fn keycode_for_char(c: char) -> CGKeyCode {
+  if let Some(keycode) = KEYCODE_CACHE.lock().unwrap().get(&c) {
+    return *keycode;
+  }
+
+  unsafe {
+    // Calling dispatch_sync onto the main queue while already running on
+    // it would deadlock, so build the table inline in that case instead
+    // of hopping through GCD.
+    if pthread_main_np() != 0 {
+      build_keycode_table_on_main_thread(std::ptr::null_mut());
+    } else {
+      let queue = dispatch_get_main_queue();
+      dispatch_sync_f(queue, std::ptr::null_mut(), build_keycode_table_on_main_thread);
+    }
+  }
+
+  KEYCODE_CACHE.lock().unwrap().get(&c).copied().unwrap_or(0)
+}
+
+fn keycode_for_key(key: &Key) -> Option<CGKeyCode> {
+  match key {
+    Key::BACKSPACE => Some(0x33),
+    Key::DELETE => Some(0x75),
+    Key::RETURN => Some(0x24),
+    Key::TAB => Some(0x30),
+    Key::ESCAPE => Some(0x35),
+    Key::SPACE => Some(0x31),
+    Key::ARROW_UP => Some(0x7E),
+    Key::ARROW_DOWN => Some(0x7D),
+    Key::ARROW_LEFT => Some(0x7B),
+    Key::ARROW_RIGHT => Some(0x7C),
+    Key::HOME => Some(0x73),
+    Key::END => Some(0x77),
+    Key::PAGE_UP => Some(0x74),
+    Key::PAGE_DOWN => Some(0x79),
+    Key::RAW(c) => Some(keycode_for_char(*c)),
+    Key::ALT | Key::CTRL | Key::SHIFT | Key::META => None,
+  }
+}
+
+fn modifier_flag(key: &Key) -> Option<CGEventFlags> {
+  match key {
+    Key::META => Some(K_CG_EVENT_FLAG_MASK_COMMAND),
+    Key::ALT => Some(K_CG_EVENT_FLAG_MASK_ALTERNATE),
+    Key::CTRL => Some(K_CG_EVENT_FLAG_MASK_CONTROL),
+    Key::SHIFT => Some(K_CG_EVENT_FLAG_MASK_SHIFT),
+    _ => None,
+  }
+}
+
+pub struct MacInjector {}
+
+impl MacInjector {
+  pub fn new() -> MacInjector {
+    MacInjector {}
+  }
+}
+
+impl Injector for MacInjector {
+  fn send_string(&self, string: &str, options: InjectionOptions) -> Result<()> {
+    let keys: Vec<Key> = string.chars().map(Key::RAW).collect();
+    self.send_keys(&keys, options)
+  }
+
+  fn send_keys(&self, keys: &[Key], options: InjectionOptions) -> Result<()> {
+    for key in keys {
+      if let Some(virtual_key) = keycode_for_key(key) {
+        post_key_event(virtual_key, 0, options.delay);
+      }
+    }
+    Ok(())
+  }
+
+  // Posts a synthetic key combination (e.g. CMD+SHIFT+V) by building a
+  // CGEvent per non-modifier key, OR-ing the requested modifier masks
+  // onto it via CGEventSetFlags, and posting down-then-up events through
+  // CGEventPost, honoring InjectionOptions.delay between events.
+  fn send_key_combination(&self, keys: &[Key], options: InjectionOptions) -> Result<()> {
+    let flags = keys
+      .iter()
+      .filter_map(modifier_flag)
+      .fold(0u64, |acc, flag| acc | flag);
+
+    for key in keys {
+      if let Some(virtual_key) = keycode_for_key(key) {
+        post_key_event(virtual_key, flags, options.delay);
+      }
+    }
+
+    Ok(())
+  }
+}
+
+fn post_key_event(virtual_key: CGKeyCode, flags: CGEventFlags, delay: i32) {
+  unsafe {
+    let source: CGEventSourceRef = std::ptr::null_mut();
+
+    let key_down = CGEventCreateKeyboardEvent(source, virtual_key, true);
+    CGEventSetFlags(key_down, flags);
+    CGEventPost(K_CG_HID_EVENT_TAP, key_down);
+    CFRelease(key_down);
+
+    if delay > 0 {
+      thread::sleep(Duration::from_millis(delay as u64));
+    }
+
+    let key_up = CGEventCreateKeyboardEvent(source, virtual_key, false);
+    CGEventSetFlags(key_up, flags);
+    CGEventPost(K_CG_HID_EVENT_TAP, key_up);
+    CFRelease(key_up);
+
+    if delay > 0 {
+      thread::sleep(Duration::from_millis(delay as u64));
+    }
+  }
+}
@@ -0,0 +1,7 @@
+use crate::matcher::Match;
+
+/// The fully resolved set of matches espanso should expand, loaded from
+/// the user's configuration files.
+pub struct Configs {
+    pub matches: Vec<Match>,
+}
@@ -1,24 +1,30 @@
 use std::sync::mpsc::Sender;
-use std::os::raw::c_void;
+use std::sync::Mutex;
+use std::os::raw::{c_void, c_int, c_ulong, c_uchar};
+use std::collections::HashMap;
+use std::ptr;
 use crate::event::*;
 use crate::event::KeyModifier::*;
 use crate::bridge::linux::*;
 
 #[repr(C)]
 pub struct LinuxContext {
-    pub send_channel: Sender<Event>
+    pub send_channel: Sender<Event>,
+    modifier_keymap: Mutex<HashMap<i32, KeyModifier>>,
 }
 
 impl LinuxContext {
     pub fn new(send_channel: Sender<Event>) -> Box<LinuxContext> {
         let context = Box::new(LinuxContext {
             send_channel,
+            modifier_keymap: Mutex::new(build_modifier_keymap()),
         });
 
         unsafe {
             let context_ptr = &*context as *const LinuxContext as *const c_void;
 
             register_keypress_callback(keypress_callback);
+            register_mapping_notify_callback(mapping_notify_callback);
 
             initialize(context_ptr);  // TODO: check initialization return codes
         }
@@ -41,6 +47,103 @@ impl Drop for LinuxContext {
     }
 }
 
+// Xlib/Xkb modifier keymap resolution
+//
+// X11 keycodes are layout/xmodmap-dependent, so rather than hardcoding
+// the keycodes for the modifiers we care about, we ask the X server which
+// physical keycodes are currently bound to each of its 8 modifier classes
+// (Shift, Lock, Control, Mod1..Mod5) and resolve each of those keycodes to
+// a keysym to figure out which espanso KeyModifier it corresponds to.
+
+const SHIFT_MAPINDEX: usize = 0;
+const CONTROL_MAPINDEX: usize = 2;
+
+#[repr(C)]
+struct XModifierKeymap {
+    max_keypermod: c_int,
+    modifiermap: *mut c_uchar,
+}
+
+type XKeysym = c_ulong;
+
+extern "C" {
+    fn XOpenDisplay(display_name: *const c_void) -> *mut c_void;
+    fn XCloseDisplay(display: *mut c_void) -> c_int;
+    fn XGetModifierMapping(display: *mut c_void) -> *mut XModifierKeymap;
+    fn XFreeModifiermap(modmap: *mut XModifierKeymap) -> c_int;
+    fn XkbKeycodeToKeysym(display: *mut c_void, keycode: c_uchar, group: c_int, level: c_int) -> XKeysym;
+    fn XKeysymToString(keysym: XKeysym) -> *const i8;
+}
+
+// Builds a map from raw X11 keycode to the espanso KeyModifier it currently
+// represents, by querying the live XGetModifierMapping table instead of
+// relying on hardcoded keycodes.
+fn build_modifier_keymap() -> HashMap<i32, KeyModifier> {
+    let mut map = HashMap::new();
+
+    unsafe {
+        let display = XOpenDisplay(ptr::null());
+        if display.is_null() {
+            return map;
+        }
+
+        let modmap = XGetModifierMapping(display);
+        if !modmap.is_null() {
+            let max_keypermod = (*modmap).max_keypermod as usize;
+
+            // The modifiermap is a flat array of 8 * max_keypermod keycodes,
+            // one row per modifier class (Shift, Lock, Control, Mod1..Mod5).
+            for mod_index in 0..8 {
+                for slot in 0..max_keypermod {
+                    let offset = mod_index * max_keypermod + slot;
+                    let keycode = *(*modmap).modifiermap.add(offset);
+
+                    if keycode == 0 {
+                        continue;
+                    }
+
+                    if let Some(modifier) = resolve_modifier(display, keycode, mod_index) {
+                        map.insert(keycode as i32, modifier);
+                    }
+                }
+            }
+
+            XFreeModifiermap(modmap);
+        }
+
+        XCloseDisplay(display);
+    }
+
+    map
+}
+
+fn resolve_modifier(display: *mut c_void, keycode: c_uchar, mod_index: usize) -> Option<KeyModifier> {
+    let keysym = unsafe { XkbKeycodeToKeysym(display, keycode, 0, 0) };
+    let name = unsafe {
+        let ptr = XKeysymToString(keysym);
+        if ptr.is_null() {
+            return None;
+        }
+        std::ffi::CStr::from_ptr(ptr).to_string_lossy().into_owned()
+    };
+
+    match name.as_str() {
+        "Super_L" | "Super_R" => Some(META),
+        "Alt_L" | "Alt_R" | "Meta_L" | "Meta_R" => Some(ALT),
+        "Control_L" | "Control_R" => Some(CTRL),
+        "Shift_L" | "Shift_R" => Some(SHIFT),
+        _ => {
+            // Fall back to the modifier class itself, in case the keysym
+            // isn't one of the well-known modifier names above.
+            match mod_index {
+                SHIFT_MAPINDEX => Some(SHIFT),
+                CONTROL_MAPINDEX => Some(CTRL),
+                _ => None,
+            }
+        }
+    }
+}
+
 // Native bridge code
 
 extern fn keypress_callback(_self: *mut c_void, raw_buffer: *const u8, len: i32,
@@ -59,14 +162,12 @@ extern fn keypress_callback(_self: *mut c_void, raw_buffer: *const u8, len: i32,
                 (*_self).send_channel.send(event).unwrap();
             }
         }else{  // Modifier event
-            let modifier: Option<KeyModifier> = match key_code {
-                133 => Some(META),
-                50 => Some(SHIFT),
-                64 => Some(ALT),
-                37 => Some(CTRL),
-                22 => Some(BACKSPACE),
-                _ => None,
-            };
+            let modifier = (*_self).modifier_keymap.lock().unwrap().get(&key_code).copied();
+
+            // BACKSPACE isn't part of the X11 modifier mapping, so it's
+            // kept as an explicit fallback rather than being resolved
+            // dynamically.
+            let modifier = modifier.or_else(|| if key_code == 22 { Some(BACKSPACE) } else { None });
 
             if let Some(modifier) = modifier {
                 let event = Event::Key(KeyEvent::Modifier(modifier));
@@ -74,4 +175,14 @@ extern fn keypress_callback(_self: *mut c_void, raw_buffer: *const u8, len: i32,
             }
         }
     }
-}
\ No newline at end of file
+}
+
+// Invoked by the native bridge whenever an X11 MappingNotify event is
+// received, so that live xmodmap changes are reflected without a restart.
+extern fn mapping_notify_callback(_self: *mut c_void) {
+    unsafe {
+        let _self = _self as *mut LinuxContext;
+        let mut keymap = (*_self).modifier_keymap.lock().unwrap();
+        *keymap = build_modifier_keymap();
+    }
+}
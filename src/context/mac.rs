@@ -0,0 +1,133 @@
+use std::sync::mpsc::Sender;
+use std::os::raw::c_void;
+use crate::event::*;
+use crate::event::KeyModifier::*;
+use crate::bridge::mac::*;
+
+#[repr(C)]
+pub struct MacContext {
+    pub send_channel: Sender<Event>
+}
+
+impl MacContext {
+    pub fn new(send_channel: Sender<Event>) -> Box<MacContext> {
+        let context = Box::new(MacContext {
+            send_channel,
+        });
+
+        unsafe {
+            let context_ptr = &*context as *const MacContext as *const c_void;
+
+            register_keypress_callback(keypress_callback);
+
+            initialize(context_ptr);  // TODO: check initialization return codes
+        }
+
+        context
+    }
+}
+
+impl super::Context for MacContext {
+    fn eventloop(&self) {
+        unsafe {
+            eventloop();
+        }
+    }
+}
+
+impl Drop for MacContext {
+    fn drop(&mut self) {
+        unsafe { cleanup(); }
+    }
+}
+
+// Fn-key translation
+//
+// On laptop keyboards, the Fn key reinterprets a handful of physical keys
+// (arrows, delete, ...) as other logical keys rather than acting as a
+// regular modifier. This mirrors Emacs' fn_keycode_to_keycode_table: a
+// lookup that, when the Fn mask is set, maps a physical keycode to the
+// logical keycode it represents. The table only lists keys with an
+// Fn-specific destination; any other key pressed with Fn held is not
+// translated, and Fn itself is then reported as a modifier. There is no
+// entry that maps a key to itself to keep Fn from being reported for it.
+const FN_KEYCODE_TO_KEYCODE_TABLE: &[(i32, i32)] = &[
+    (0x7B, 0x73), // Left Arrow    -> Home
+    (0x7C, 0x77), // Right Arrow   -> End
+    (0x7E, 0x74), // Up Arrow      -> Page Up
+    (0x7D, 0x79), // Down Arrow    -> Page Down
+    (0x33, 0x75), // Delete        -> Forward Delete
+];
+
+fn translate_fn_keycode(key_code: i32) -> i32 {
+    FN_KEYCODE_TO_KEYCODE_TABLE
+        .iter()
+        .find(|(from, _)| *from == key_code)
+        .map(|(_, to)| *to)
+        .unwrap_or(key_code)
+}
+
+// Maps a *translated* destination keycode (the right-hand side of
+// FN_KEYCODE_TO_KEYCODE_TABLE) to the logical Key espanso should report.
+fn logical_key_for_translated_keycode(translated_key_code: i32) -> Option<Key> {
+    match translated_key_code {
+        0x73 => Some(Key::HOME),
+        0x77 => Some(Key::END),
+        0x74 => Some(Key::PAGE_UP),
+        0x79 => Some(Key::PAGE_DOWN),
+        0x75 => Some(Key::FORWARD_DELETE),
+        _ => None,
+    }
+}
+
+// Native bridge code
+
+extern fn keypress_callback(_self: *mut c_void, raw_buffer: *const u8, len: i32,
+                            is_modifier: i32, key_code: i32, fn_mask: i32) {
+    unsafe {
+        let _self = _self as *mut MacContext;
+
+        if is_modifier == 0 {  // Char event
+            // Fn-translated keys (arrows, delete, ...) never carry printable
+            // text, so they always arrive here with is_modifier != 0; no Fn
+            // translation applies to this branch.
+            let buffer = std::slice::from_raw_parts(raw_buffer, len as usize);
+            let r = String::from_utf8_lossy(buffer).chars().nth(0);
+
+            if let Some(c) = r {
+                let event = Event::Key(KeyEvent::Char(c));
+                (*_self).send_channel.send(event).unwrap();
+            }
+        } else if fn_mask != 0 {  // Fn-combined key
+            let translated_key_code = translate_fn_keycode(key_code);
+
+            if translated_key_code != key_code {
+                // This key has an Fn-specific destination (e.g. Fn+Left -> Home):
+                // report the translated logical key instead of a modifier.
+                if let Some(key) = logical_key_for_translated_keycode(translated_key_code) {
+                    let event = Event::Key(KeyEvent::Key(key));
+                    (*_self).send_channel.send(event).unwrap();
+                }
+            } else {
+                // The Fn mask is set, but this key has no Fn-specific destination,
+                // so Fn itself is reported as a modifier.
+                let event = Event::Key(KeyEvent::Modifier(FN));
+                (*_self).send_channel.send(event).unwrap();
+            }
+        } else {  // Modifier event
+            let modifier: Option<KeyModifier> = match key_code {
+                0x37 => Some(META),      // Command
+                0x38 | 0x3C => Some(SHIFT),
+                0x3A | 0x3D => Some(ALT), // Option
+                0x3B | 0x3E => Some(CTRL),
+                0x33 => Some(BACKSPACE),
+                _ => None,
+            };
+
+            if let Some(modifier) = modifier {
+                let event = Event::Key(KeyEvent::Modifier(modifier));
+                (*_self).send_channel.send(event).unwrap();
+            }
+        }
+    }
+}
@@ -1,20 +1,73 @@
 use crate::matcher::{Match, MatchReceiver};
 use crate::keyboard::KeyboardSender;
 use crate::config::Configs;
+use crate::window::WindowProvider;
 
 pub struct Engine<S> where S: KeyboardSender {
     sender: S,
     configs: Configs,
+    window_provider: Option<Box<dyn WindowProvider>>,
 }
 
 impl <S> Engine<S> where S: KeyboardSender{
     pub fn new(sender: S, configs: Configs) -> Engine<S> where S: KeyboardSender {
-        Engine{sender, configs }
+        Engine{sender, configs, window_provider: None }
+    }
+
+    pub fn new_with_window_provider(sender: S, configs: Configs, window_provider: Box<dyn WindowProvider>) -> Engine<S> where S: KeyboardSender {
+        Engine{sender, configs, window_provider: Some(window_provider) }
+    }
+
+    // Returns false when the given match should be skipped because the
+    // currently focused application doesn't satisfy its filters.
+    fn passes_app_filter(&self, m: &Match) -> bool {
+        let window_provider = match &self.window_provider {
+            Some(provider) => provider,
+            None => return true,
+        };
+
+        let class = window_provider.active_window_class();
+        let title = window_provider.active_window_title();
+
+        if let Some(exclude_apps) = &m.exclude_apps {
+            if let Some(class) = &class {
+                if exclude_apps.iter().any(|app| app == class) {
+                    return false;
+                }
+            }
+        }
+
+        if let Some(apps) = &m.apps {
+            let class_matches = class.as_ref().map_or(false, |class| apps.iter().any(|app| app == class));
+            if !class_matches {
+                return false;
+            }
+        }
+
+        if let Some(filter_class) = &m.filter_class {
+            let class_matches = class.as_ref().map_or(false, |class| filter_class.is_match(class));
+            if !class_matches {
+                return false;
+            }
+        }
+
+        if let Some(filter_title) = &m.filter_title {
+            let title_matches = title.as_ref().map_or(false, |title| filter_title.is_match(title));
+            if !title_matches {
+                return false;
+            }
+        }
+
+        true
     }
 }
 
 impl <S> MatchReceiver for Engine<S> where S: KeyboardSender{
     fn on_match(&self, m: &Match) {
+        if !self.passes_app_filter(m) {
+            return;
+        }
+
         self.sender.delete_string(m.trigger.len() as i32);
 
         // To handle newlines, substitute each "\n" char with an Enter key press.
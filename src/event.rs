@@ -0,0 +1,35 @@
+/// The modifier keys that espanso is able to recognize coming from
+/// the platform-specific detectors.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum KeyModifier {
+    META,
+    SHIFT,
+    ALT,
+    CTRL,
+    BACKSPACE,
+    FN,
+}
+
+/// Logical, non-modifier keys that a detector can report directly, e.g.
+/// after resolving a physical keycode through a platform-specific
+/// translation table (such as macOS' Fn-key remapping).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Key {
+    HOME,
+    END,
+    PAGE_UP,
+    PAGE_DOWN,
+    FORWARD_DELETE,
+}
+
+#[derive(Debug, Clone)]
+pub enum KeyEvent {
+    Char(char),
+    Modifier(KeyModifier),
+    Key(Key),
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    Key(KeyEvent),
+}
@@ -0,0 +1,30 @@
+use regex::Regex;
+
+/// A single text expansion rule: when `trigger` is typed, it gets deleted
+/// and replaced with `replace`, unless one of the optional app filters
+/// below excludes the currently focused window.
+pub struct Match {
+    pub trigger: String,
+    pub replace: String,
+
+    // Only expand this match when the active window's class matches this
+    // regex. `None` means "no filtering based on class".
+    pub filter_class: Option<Regex>,
+
+    // Only expand this match when the active window's title matches this
+    // regex. `None` means "no filtering based on title".
+    pub filter_title: Option<Regex>,
+
+    // Only expand this match when the active window's class is one of
+    // these. Combined with the other filters using AND: when both `apps`
+    // and `filter_class` are given, both must match.
+    pub apps: Option<Vec<String>>,
+
+    // Never expand this match when the active window's class is one of
+    // these, regardless of the other filters.
+    pub exclude_apps: Option<Vec<String>>,
+}
+
+pub trait MatchReceiver {
+    fn on_match(&self, m: &Match);
+}
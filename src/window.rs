@@ -0,0 +1,149 @@
+/// Provides information about the currently focused window, so that
+/// matches can be filtered based on the active application.
+pub trait WindowProvider {
+    /// Returns the WM_CLASS (or equivalent) of the currently active window.
+    fn active_window_class(&self) -> Option<String>;
+
+    /// Returns the title of the currently active window.
+    fn active_window_title(&self) -> Option<String>;
+}
+
+#[cfg(target_os = "linux")]
+pub use self::x11::X11WindowProvider;
+
+#[cfg(target_os = "linux")]
+mod x11 {
+    use super::WindowProvider;
+    use std::os::raw::c_void;
+    use std::ptr;
+
+    // Minimal Xlib bindings, following the same raw extern "C" approach
+    // used by the rest of the context/detector code.
+    extern "C" {
+        fn XOpenDisplay(display_name: *const c_void) -> *mut c_void;
+        fn XDefaultRootWindow(display: *mut c_void) -> u64;
+        fn XInternAtom(display: *mut c_void, atom_name: *const i8, only_if_exists: i32) -> u64;
+        fn XGetWindowProperty(
+            display: *mut c_void,
+            w: u64,
+            property: u64,
+            long_offset: i64,
+            long_length: i64,
+            delete: i32,
+            req_type: u64,
+            actual_type_return: *mut u64,
+            actual_format_return: *mut i32,
+            nitems_return: *mut u64,
+            bytes_after_return: *mut u64,
+            prop_return: *mut *mut u8,
+        ) -> i32;
+        fn XGetClassHint(display: *mut c_void, w: u64, class_hints_return: *mut XClassHint) -> i32;
+        fn XFree(data: *mut c_void) -> i32;
+    }
+
+    #[repr(C)]
+    struct XClassHint {
+        res_name: *mut i8,
+        res_class: *mut i8,
+    }
+
+    pub struct X11WindowProvider {
+        display: *mut c_void,
+    }
+
+    impl X11WindowProvider {
+        pub fn new() -> Option<X11WindowProvider> {
+            let display = unsafe { XOpenDisplay(ptr::null()) };
+            if display.is_null() {
+                return None;
+            }
+            Some(X11WindowProvider { display })
+        }
+
+        fn active_window(&self) -> Option<u64> {
+            unsafe {
+                let root = XDefaultRootWindow(self.display);
+                let atom_name = std::ffi::CString::new("_NET_ACTIVE_WINDOW").unwrap();
+                let atom = XInternAtom(self.display, atom_name.as_ptr(), 0);
+
+                let mut actual_type = 0u64;
+                let mut actual_format = 0i32;
+                let mut nitems = 0u64;
+                let mut bytes_after = 0u64;
+                let mut prop: *mut u8 = ptr::null_mut();
+
+                let status = XGetWindowProperty(
+                    self.display, root, atom, 0, 1, 0, 0,
+                    &mut actual_type, &mut actual_format, &mut nitems, &mut bytes_after,
+                    &mut prop,
+                );
+
+                if status != 0 || prop.is_null() || nitems == 0 {
+                    return None;
+                }
+
+                let window = *(prop as *const u64);
+                XFree(prop as *mut c_void);
+
+                Some(window)
+            }
+        }
+    }
+
+    impl WindowProvider for X11WindowProvider {
+        fn active_window_class(&self) -> Option<String> {
+            let window = self.active_window()?;
+
+            unsafe {
+                let mut class_hint = XClassHint { res_name: ptr::null_mut(), res_class: ptr::null_mut() };
+                if XGetClassHint(self.display, window, &mut class_hint) == 0 {
+                    return None;
+                }
+
+                if class_hint.res_class.is_null() {
+                    return None;
+                }
+
+                let class = std::ffi::CStr::from_ptr(class_hint.res_class).to_string_lossy().into_owned();
+
+                if !class_hint.res_name.is_null() {
+                    XFree(class_hint.res_name as *mut c_void);
+                }
+                XFree(class_hint.res_class as *mut c_void);
+
+                Some(class)
+            }
+        }
+
+        fn active_window_title(&self) -> Option<String> {
+            let window = self.active_window()?;
+
+            unsafe {
+                let atom_name = std::ffi::CString::new("_NET_WM_NAME").unwrap();
+                let atom = XInternAtom(self.display, atom_name.as_ptr(), 0);
+
+                let mut actual_type = 0u64;
+                let mut actual_format = 0i32;
+                let mut nitems = 0u64;
+                let mut bytes_after = 0u64;
+                let mut prop: *mut u8 = ptr::null_mut();
+
+                let status = XGetWindowProperty(
+                    self.display, window, atom, 0, 1024, 0, 0,
+                    &mut actual_type, &mut actual_format, &mut nitems, &mut bytes_after,
+                    &mut prop,
+                );
+
+                if status != 0 || prop.is_null() {
+                    return None;
+                }
+
+                let buffer = std::slice::from_raw_parts(prop, nitems as usize);
+                let title = String::from_utf8_lossy(buffer).into_owned();
+                XFree(prop as *mut c_void);
+
+                Some(title)
+            }
+        }
+    }
+}